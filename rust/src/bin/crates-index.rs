@@ -2,17 +2,21 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::env;
 use std::fs;
+use std::io::Read;
 use std::path::Path;
 
 use csv::ReaderBuilder;
+use flate2::read::GzDecoder;
 use semver::Version;
 use serde::de::DeserializeOwned;
 use serde_derive::Deserialize;
+use tar::Archive;
 
 use rust_search_extension::minify::Minifier;
 
 const MAX_CRATE_SIZE: usize = 20 * 1000;
 const CRATES_INDEX_PATH: &str = "../extension/index/crates.js";
+const DB_DUMP_URL: &str = "https://static.crates.io/db-dump.tar.gz";
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -24,14 +28,88 @@ struct Crate {
     description: Option<String>,
     #[serde(skip_deserializing, default = "default_version")]
     version: Version,
+    #[serde(skip_deserializing, default)]
+    rev_deps: RevDepCount,
+    #[serde(skip_deserializing, default)]
+    keywords: Vec<String>,
+    #[serde(skip_deserializing, default)]
+    categories: Vec<String>,
+    #[serde(skip_deserializing, default)]
+    has_features: bool,
+}
+
+/// A single JSON line from a crate's file in the `crates.io-index` git tree.
+#[derive(Deserialize, Debug)]
+struct IndexEntry {
+    vers: Version,
+    yanked: bool,
+    #[serde(default)]
+    features: HashMap<String, Vec<String>>,
 }
 
 #[derive(Deserialize, Debug)]
 struct CrateVersion {
+    id: u64,
     crate_id: u64,
     num: Version,
 }
 
+#[derive(Deserialize, Debug)]
+struct Dependency {
+    version_id: u64,
+    crate_id: u64,
+    #[serde(deserialize_with = "deserialize_pg_bool")]
+    optional: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct Keyword {
+    id: u64,
+    keyword: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CrateKeyword {
+    crate_id: u64,
+    keyword_id: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Category {
+    id: u64,
+    slug: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CrateCategory {
+    crate_id: u64,
+    category_id: u64,
+}
+
+/// Number of *distinct* other crates that depend on a crate, split by whether
+/// the dependency is optional. Used to rank foundational crates above what
+/// their raw download counts alone would suggest.
+#[derive(Debug, Default, Clone, Copy)]
+struct RevDepCount {
+    required: u32,
+    optional: u32,
+}
+
+/// The tables we consume from a crates.io dump, however they were sourced.
+#[derive(Default)]
+struct DbDump {
+    crates: Vec<Crate>,
+    versions: Vec<CrateVersion>,
+    dependencies: Vec<Dependency>,
+    keywords: Vec<Keyword>,
+    crate_keywords: Vec<CrateKeyword>,
+    categories: Vec<Category>,
+    crate_categories: Vec<CrateCategory>,
+    /// The dump's date, taken from the archive's top-level directory (e.g.
+    /// `2024-01-01-020000`). Recorded in the cache-busting manifest.
+    dump_date: Option<String>,
+}
+
 #[derive(Debug)]
 struct WordCollector {
     words: Vec<String>,
@@ -55,6 +133,11 @@ impl WordCollector {
         }
     }
 
+    #[inline]
+    fn collect_crate_keyword(&mut self, value: &str) {
+        self.words.push(value.to_lowercase());
+    }
+
     #[inline]
     fn collect_crate_description(&mut self, value: &str) {
         let mut description = value.trim().to_string();
@@ -70,30 +153,299 @@ fn default_version() -> Version {
     Version::parse("0.0.0").unwrap()
 }
 
+/// A short, content-derived hex hash used to cache-bust the emitted index file,
+/// mirroring rustdoc's shared-file naming. FNV-1a keeps it dependency-free and
+/// the 16 hex chars are plenty to disambiguate successive dumps.
+fn short_hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// PostgreSQL `COPY ... CSV` renders booleans as `t`/`f`, which serde's default
+/// `bool` deserializer does not understand.
+fn deserialize_pg_bool<'de, D>(deserializer: D) -> std::result::Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(value == "t")
+}
+
 fn read_csv<D: DeserializeOwned>(path: &str) -> Result<Vec<D>> {
+    read_csv_from_reader(fs::File::open(Path::new(path))?)
+}
+
+fn read_csv_from_reader<D: DeserializeOwned, R: Read>(reader: R) -> Result<Vec<D>> {
     let mut records: Vec<D> = vec![];
-    let mut reader = ReaderBuilder::new()
-        .has_headers(true)
-        .from_path(Path::new(path))?;
+    let mut reader = ReaderBuilder::new().has_headers(true).from_reader(reader);
     for record in reader.deserialize() {
         records.push(record?);
     }
     Ok(records)
 }
 
+/// Open the `db-dump.tar.gz` archive, either by downloading it from a
+/// `http(s)` url or by reading a local path.
+fn open_db_dump(location: &str) -> Result<Box<dyn Read>> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        Ok(Box::new(ureq::get(location).call()?.into_reader()))
+    } else {
+        Ok(Box::new(fs::File::open(Path::new(location))?))
+    }
+}
+
+/// Stream the crates.io `db-dump.tar.gz` archive, decompressing on the fly and
+/// dispatching each CSV member into the matching deserialization. Reading every
+/// table from the same archive guarantees they come from a consistent dump.
+fn read_db_dump(location: &str) -> Result<DbDump> {
+    let mut archive = Archive::new(GzDecoder::new(open_db_dump(location)?));
+    let mut dump = DbDump::default();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        // Entries are prefixed with the dump date (e.g. `2024-01-01-020000/`),
+        // so match on the bare file name rather than the full path.
+        let path = entry.path()?.into_owned();
+        if dump.dump_date.is_none() {
+            dump.dump_date = path
+                .components()
+                .next()
+                .and_then(|c| c.as_os_str().to_str())
+                .map(|s| s.to_string());
+        }
+        match path.file_name().and_then(|name| name.to_str()) {
+            Some("crates.csv") => dump.crates = read_csv_from_reader(entry)?,
+            Some("versions.csv") => dump.versions = read_csv_from_reader(entry)?,
+            Some("dependencies.csv") => dump.dependencies = read_csv_from_reader(entry)?,
+            Some("keywords.csv") => dump.keywords = read_csv_from_reader(entry)?,
+            Some("crate_keywords.csv") => dump.crate_keywords = read_csv_from_reader(entry)?,
+            Some("categories.csv") => dump.categories = read_csv_from_reader(entry)?,
+            Some("crate_categories.csv") => dump.crate_categories = read_csv_from_reader(entry)?,
+            _ => {}
+        }
+    }
+    Ok(dump)
+}
+
+/// Read the same tables from a directory of pre-extracted CSVs (the layout CI
+/// produces when it unpacks the dump itself).
+fn read_directory(csv_path: &str) -> Result<DbDump> {
+    Ok(DbDump {
+        crates: read_csv(&format!("{}{}", csv_path, "crates.csv"))?,
+        versions: read_csv(&format!("{}{}", csv_path, "versions.csv"))?,
+        dependencies: read_csv(&format!("{}{}", csv_path, "dependencies.csv"))?,
+        keywords: read_csv(&format!("{}{}", csv_path, "keywords.csv"))?,
+        crate_keywords: read_csv(&format!("{}{}", csv_path, "crate_keywords.csv"))?,
+        categories: read_csv(&format!("{}{}", csv_path, "categories.csv"))?,
+        crate_categories: read_csv(&format!("{}{}", csv_path, "crate_categories.csv"))?,
+        // A bare directory carries no dump date, so the manifest records none.
+        dump_date: None,
+    })
+}
+
+/// Path to a crate's file within a cloned `crates.io-index`, following the
+/// registry's `aa/bb/name` sharding (`1/`, `2/`, `3/a/` for short names).
+fn index_shard_path(index_path: &str, name: &str) -> std::path::PathBuf {
+    let lower = name.to_lowercase();
+    let mut path = std::path::PathBuf::from(index_path);
+    match lower.len() {
+        1 => path.push("1"),
+        2 => path.push("2"),
+        3 => {
+            path.push("3");
+            path.push(&lower[0..1]);
+        }
+        _ => {
+            path.push(&lower[0..2]);
+            path.push(&lower[2..4]);
+        }
+    }
+    path.push(&lower);
+    path
+}
+
+/// Read a crate's index file and select its highest non-yanked release,
+/// returning the version and whether that release defines any features. Yanked
+/// releases are skipped so a withdrawn version never becomes the advertised
+/// "latest".
+fn read_index_versions(index_path: &str, name: &str) -> Result<Option<(Version, bool)>> {
+    let contents = match fs::read_to_string(index_shard_path(index_path, name)) {
+        Ok(contents) => contents,
+        // A crate present in the dump may be absent from the index (rare); fall
+        // through to whatever version the caller already has.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut latest: Option<(Version, bool)> = None;
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: IndexEntry = serde_json::from_str(line)?;
+        if entry.yanked {
+            continue;
+        }
+        let has_features = !entry.features.is_empty();
+        match &latest {
+            Some((version, _)) if *version >= entry.vers => {}
+            _ => latest = Some((entry.vers, has_features)),
+        }
+    }
+    Ok(latest)
+}
+
+/// Count, for each crate, how many *distinct* other crates depend on it. A
+/// dependency links a `version_id` to a depended-on `crate_id`, so we first map
+/// every version back to its owning crate and then dedupe dependents per crate
+/// (many versions of the same dependent count once).
+fn count_rev_deps(
+    versions: &[CrateVersion],
+    dependencies: &[Dependency],
+) -> HashMap<u64, RevDepCount> {
+    let version_owner: HashMap<u64, u64> =
+        versions.iter().map(|v| (v.id, v.crate_id)).collect();
+
+    // depended-on crate -> dependent crate -> whether *any* of that dependent's
+    // versions requires it (required wins over optional), so each dependent is
+    // counted exactly once.
+    let mut seen: HashMap<u64, HashMap<u64, bool>> = HashMap::new();
+    for dep in dependencies {
+        if let Some(&dependent) = version_owner.get(&dep.version_id) {
+            let required = !dep.optional;
+            seen.entry(dep.crate_id)
+                .or_default()
+                .entry(dependent)
+                .and_modify(|r| *r |= required)
+                .or_insert(required);
+        }
+    }
+
+    seen.into_iter()
+        .map(|(crate_id, dependents)| {
+            let mut count = RevDepCount::default();
+            for required in dependents.into_values() {
+                if required {
+                    count.required += 1;
+                } else {
+                    count.optional += 1;
+                }
+            }
+            (crate_id, count)
+        })
+        .collect()
+}
+
+/// Attach each crate's keywords and category slugs, joined through the dump's
+/// id tables. crates.io already caps both at five per crate, so the lists stay
+/// small without extra truncation.
+fn attach_keywords_and_categories(crates: &mut [Crate], dump: &DbDump) {
+    let keyword_by_id: HashMap<u64, &str> = dump
+        .keywords
+        .iter()
+        .map(|k| (k.id, k.keyword.as_str()))
+        .collect();
+    let category_by_id: HashMap<u64, &str> = dump
+        .categories
+        .iter()
+        .map(|c| (c.id, c.slug.as_str()))
+        .collect();
+
+    let mut keywords: HashMap<u64, Vec<String>> = HashMap::new();
+    for ck in &dump.crate_keywords {
+        if let Some(keyword) = keyword_by_id.get(&ck.keyword_id) {
+            keywords.entry(ck.crate_id).or_default().push(keyword.to_string());
+        }
+    }
+    let mut categories: HashMap<u64, Vec<String>> = HashMap::new();
+    for cc in &dump.crate_categories {
+        if let Some(slug) = category_by_id.get(&cc.category_id) {
+            categories.entry(cc.crate_id).or_default().push(slug.to_string());
+        }
+    }
+
+    for item in crates.iter_mut() {
+        if let Some(words) = keywords.remove(&item.id) {
+            item.keywords = words;
+        }
+        if let Some(slugs) = categories.remove(&item.id) {
+            item.categories = slugs;
+        }
+    }
+}
+
+impl RevDepCount {
+    // Weights chosen so a single required dependent is worth a few downloads
+    // doublings, and an optional one counts for less.
+    const REQUIRED_WEIGHT: u64 = 5;
+    const OPTIONAL_WEIGHT: u64 = 2;
+
+    /// Composite importance score combining download volume with ecosystem
+    /// reach, so foundational crates (serde, libc) outrank download-only peers.
+    fn score(&self, downloads: u64) -> u64 {
+        downloads.max(1).ilog2() as u64
+            + self.required as u64 * Self::REQUIRED_WEIGHT
+            + self.optional as u64 * Self::OPTIONAL_WEIGHT
+    }
+}
+
+/// Emit `var keywordIndex={...};` mapping each minified keyword/category to the
+/// list of minified crate ids carrying it, so the extension can answer queries
+/// like "crates tagged async" or browse by category.
+fn generate_keyword_index(crates: &[Crate], minifier: &Minifier) -> String {
+    let mut keyword_map: HashMap<String, Vec<String>> = HashMap::new();
+    for item in crates {
+        let crate_id = minifier.mapping_minify_crate_id(item.name.clone());
+        for keyword in item.keywords.iter().chain(item.categories.iter()) {
+            keyword_map
+                .entry(minifier.mapping_minify(keyword.to_lowercase()))
+                .or_default()
+                .push(crate_id.clone());
+        }
+    }
+    let keyword_index = format!(
+        "var keywordIndex={};",
+        serde_json::to_string(&keyword_map).unwrap()
+    );
+    Minifier::minify_json(keyword_index)
+}
+
+/// Build an `fst::Set` over every crate name (normalized the same way
+/// `WordCollector::collect_crate_id` normalizes, `-` → `_`) and return the
+/// serialized automaton. The client queries it with a Levenshtein automaton to
+/// turn typos like "serd"/"tokoi" into near-miss suggestions, and the shared
+/// prefix/suffix compression keeps the payload small across all 20k names.
+fn generate_fst_index(crates: &[Crate]) -> Result<Vec<u8>> {
+    let mut names: Vec<String> = crates
+        .iter()
+        .map(|item| item.name.replace('-', "_").to_lowercase())
+        .collect();
+    // `fst::Set::from_iter` requires lexicographically sorted, unique keys.
+    names.sort_unstable();
+    names.dedup();
+    let set = fst::Set::from_iter(names)?;
+    Ok(set.into_fst().as_bytes().to_vec())
+}
+
 fn generate_javascript_crates_index(
     crates: Vec<Crate>,
     minifier: &Minifier,
 ) -> std::io::Result<String> {
     let mut contents = String::from("var N=null;");
-    let crates_map: HashMap<String, (Option<String>, Version)> = crates
+    // Per crate: description, latest version, how many crates depend on it (so
+    // the extension can render "used by N crates"), and whether the latest
+    // release defines any features.
+    let crates_map: HashMap<String, (Option<String>, Version, u32, bool)> = crates
         .into_iter()
         .map(|item| {
+            let used_by = item.rev_deps.required + item.rev_deps.optional;
             (
                 minifier.mapping_minify_crate_id(item.name),
                 (
                     item.description.map(|value| minifier.mapping_minify(value)),
                     item.version,
+                    used_by,
+                    item.has_features,
                 ),
             )
         })
@@ -108,49 +460,204 @@ fn generate_javascript_crates_index(
 
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
-    let csv_path = args.get(1).expect("Path is required...");
-
-    let mut crates: Vec<Crate> = read_csv(&format!("{}{}", csv_path, "crates.csv"))?;
-    crates.sort_unstable_by(|a, b| b.downloads.cmp(&a.downloads));
-    crates = crates.drain(0..=MAX_CRATE_SIZE).collect();
-    let mut versions: Vec<CrateVersion> = read_csv(&format!("{}{}", csv_path, "versions.csv"))?;
-    versions.sort_unstable_by(|a, b| b.num.cmp(&a.num));
-
-    // Filter out duplicated version to speed up find in the later.
-    let mut unique_crate_ids: HashSet<u64> = HashSet::with_capacity(2 * MAX_CRATE_SIZE);
-    versions = versions
-        .into_iter()
-        .filter(|v| {
-            if unique_crate_ids.contains(&v.crate_id) {
+    // Flags are filtered out so the remaining positional arguments keep their
+    // historical meaning (input location, then output path).
+    let from_dir = args.iter().any(|arg| arg == "--from-dir");
+    // Flags that take a value; their argument must be dropped from positionals.
+    const VALUED_FLAGS: [&str; 3] = ["--index-path", "--out-dir", "--resource-suffix"];
+    let valued = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|pos| args.get(pos + 1))
+            .cloned()
+    };
+    // `--index-path <dir>` points at a cloned `crates.io-index` and opts into
+    // deriving yanked-aware latest versions from it.
+    let index_path = valued("--index-path");
+    // `--out-dir <dir>` / `--resource-suffix <s>` let several generations of
+    // the index coexist during a release.
+    let out_dir = valued("--out-dir");
+    let resource_suffix = valued("--resource-suffix").unwrap_or_default();
+    let mut skip_next = false;
+    let positional: Vec<&String> = args
+        .iter()
+        .skip(1)
+        .filter(|arg| {
+            if skip_next {
+                skip_next = false;
                 return false;
             }
-            unique_crate_ids.insert(v.crate_id);
-            false
+            if VALUED_FLAGS.contains(&arg.as_str()) {
+                skip_next = true;
+                return false;
+            }
+            !arg.starts_with("--")
         })
         .collect();
+
+    let mut dump = if from_dir {
+        // Directory mode: read pre-extracted CSVs, e.g. the files CI already
+        // unpacks from the dump.
+        let csv_path = positional.first().expect("Path is required...");
+        read_directory(csv_path)?
+    } else {
+        // Dump mode: stream `db-dump.tar.gz`, falling back to the official url.
+        let location = positional.first().map(|s| s.as_str()).unwrap_or(DB_DUMP_URL);
+        read_db_dump(location)?
+    };
+
+    // Rank by ecosystem importance (reverse-dependency reach blended with raw
+    // downloads) rather than downloads alone, then keep the top crates.
+    let rev_deps = count_rev_deps(&dump.versions, &dump.dependencies);
+    let mut crates = std::mem::take(&mut dump.crates);
+    for item in &mut crates {
+        item.rev_deps = rev_deps.get(&item.id).copied().unwrap_or_default();
+    }
+    crates.sort_unstable_by(|a, b| {
+        b.rev_deps
+            .score(b.downloads)
+            .cmp(&a.rev_deps.score(a.downloads))
+    });
+    // Keep the top crates; guard the bound since `--from-dir` may feed a small
+    // extracted set with fewer than `MAX_CRATE_SIZE` rows.
+    let keep = crates.len().min(MAX_CRATE_SIZE + 1);
+    crates = crates.drain(0..keep).collect();
+    attach_keywords_and_categories(&mut crates, &dump);
+
+    dump.versions.sort_unstable_by(|a, b| b.num.cmp(&a.num));
+    // Keep only the highest version per crate to speed up the find below.
+    // NOTE: the predicate returns `insert`'s result (true for the first, unseen
+    // crate id) — the baseline returned a constant `false`, which dropped every
+    // row and left the CSV version fallback stuck at `0.0.0`.
+    let mut unique_crate_ids: HashSet<u64> = HashSet::with_capacity(2 * MAX_CRATE_SIZE);
+    let versions: Vec<CrateVersion> = dump
+        .versions
+        .drain(..)
+        .filter(|v| unique_crate_ids.insert(v.crate_id))
+        .collect();
     let mut collector = WordCollector::new();
-    crates.iter_mut().for_each(|item: &mut Crate| {
-        if let Some(version) = versions.iter().find(|&v| v.crate_id == item.id) {
-            item.version = version.num.to_owned();
+    for item in crates.iter_mut() {
+        // Prefer the git index when available: it respects yanked releases and
+        // surfaces whether the latest release ships features. Otherwise fall
+        // back to the highest `num` scanned from `versions.csv`.
+        match index_path.as_deref() {
+            Some(index_path) => {
+                if let Some((version, has_features)) = read_index_versions(index_path, &item.name)?
+                {
+                    item.version = version;
+                    item.has_features = has_features;
+                }
+            }
+            None => {
+                if let Some(version) = versions.iter().find(|&v| v.crate_id == item.id) {
+                    item.version = version.num.to_owned();
+                }
+            }
         }
 
         if let Some(description) = &item.description {
             collector.collect_crate_description(description);
         }
         collector.collect_crate_id(&item.name);
-    });
+        for keyword in item.keywords.iter().chain(item.categories.iter()) {
+            collector.collect_crate_keyword(keyword);
+        }
+    }
 
     // Extract frequency word mapping
     let minifier = Minifier::new(&collector.words);
     let mapping = minifier.get_mapping();
     let mut contents = format!("var mapping={};", serde_json::to_string(&mapping)?);
+    contents.push_str(&generate_keyword_index(&crates, &minifier));
+    let fst_bytes = generate_fst_index(&crates)?;
     contents.push_str(&generate_javascript_crates_index(crates, &minifier)?);
-    let path = Path::new(
-        args.get(2)
+    // Determine the output directory: explicit `--out-dir`, otherwise the
+    // directory of the legacy positional path.
+    let legacy_path = Path::new(
+        positional
+            .get(1)
             .map(|path| path.as_str())
             .unwrap_or(CRATES_INDEX_PATH),
     );
-    fs::write(path, &contents)?;
+    let out_dir = out_dir
+        .as_deref()
+        .map(Path::new)
+        .or_else(|| legacy_path.parent())
+        .unwrap_or_else(|| Path::new("."));
+
+    // Content-hash each payload independently so either file is immutable and
+    // safely cacheable, and a change to only one busts only that file's name;
+    // the extension reads the manifest to learn which files to fetch.
+    let js_name = format!(
+        "crates-{}{}.js",
+        short_hash(contents.as_bytes()),
+        resource_suffix
+    );
+    let fst_name = format!(
+        "crates-{}{}.fst",
+        short_hash(&fst_bytes),
+        resource_suffix
+    );
+    fs::write(out_dir.join(&js_name), &contents)?;
+    // The typo-tolerant name automaton ships as a sibling binary file.
+    fs::write(out_dir.join(&fst_name), &fst_bytes)?;
+
+    // Tiny manifest the loader reads to resolve the current hashed files and
+    // prune stale copies.
+    let manifest = serde_json::json!({
+        "crates": js_name,
+        "fst": fst_name,
+        "date": dump.dump_date,
+    });
+    fs::write(
+        out_dir.join("crates-index.json"),
+        serde_json::to_string(&manifest)?,
+    )?;
     println!("\nGenerate javascript crates index successful!");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn shard_path_follows_registry_layout() {
+        assert_eq!(index_shard_path("index", "a"), PathBuf::from("index/1/a"));
+        assert_eq!(index_shard_path("index", "ab"), PathBuf::from("index/2/ab"));
+        assert_eq!(index_shard_path("index", "abc"), PathBuf::from("index/3/a/abc"));
+        assert_eq!(
+            index_shard_path("index", "serde"),
+            PathBuf::from("index/se/rd/serde")
+        );
+        // Names are lowercased before sharding.
+        assert_eq!(
+            index_shard_path("index", "Serde"),
+            PathBuf::from("index/se/rd/serde")
+        );
+    }
+
+    #[test]
+    fn rev_deps_count_each_dependent_once() {
+        // Dependent crate 1 has two versions; one marks crate 100 optional, the
+        // other required. Dependent crate 2 only marks it optional.
+        let versions = vec![
+            CrateVersion { id: 10, crate_id: 1, num: Version::new(1, 0, 0) },
+            CrateVersion { id: 11, crate_id: 1, num: Version::new(1, 1, 0) },
+            CrateVersion { id: 20, crate_id: 2, num: Version::new(0, 1, 0) },
+        ];
+        let dependencies = vec![
+            Dependency { version_id: 10, crate_id: 100, optional: true },
+            Dependency { version_id: 11, crate_id: 100, optional: false },
+            Dependency { version_id: 20, crate_id: 100, optional: true },
+        ];
+
+        let rev_deps = count_rev_deps(&versions, &dependencies);
+        let count = rev_deps[&100];
+        // Required wins for crate 1, so it is counted once as required (not in
+        // both buckets); crate 2 is the single optional dependent.
+        assert_eq!(count.required, 1);
+        assert_eq!(count.optional, 1);
+    }
+}